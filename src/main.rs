@@ -1,24 +1,46 @@
 use anyhow::Result;
 use clap::Parser;
 use image::ColorType;
-use indicatif::ProgressBar;
-use material::{Dielectric, Lambertian, Metallic, ScatteringResult};
-use rand::Rng;
+use material::{Dielectric, DiffuseLight, Lambertian, Metallic};
+use rand::{Rng, RngCore};
 use std::{path::PathBuf, sync::Arc};
 use ultraviolet::Vec3;
 
 mod material;
 mod random;
+mod renderer;
 mod trace;
 use random::{UniformInDisc, UniformInSphere, UniformOnSphere};
-use trace::{Probe, Ray, Scene, Sphere};
+use renderer::{DepthRenderer, NormalsRenderer, PathTracer, Renderer};
+use trace::{MovingSphere, Ray, Scene, Sphere};
 
 pub(crate) type Color = Vec3;
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum RendererKind {
+    /// Monte Carlo path tracer (the real renderer).
+    Path,
+    /// Debug AOV: hit normals mapped to RGB.
+    Normals,
+    /// Debug AOV: hit distance mapped to grayscale.
+    Depth,
+}
+
 #[derive(Debug, Parser)]
 struct Args {
     #[arg(short, long, value_name = "IMAGE", default_value = "render.png")]
     out: PathBuf,
+
+    #[arg(short, long, value_enum, default_value = "path")]
+    renderer: RendererKind,
+
+    /// Camera rays per pixel (path tracer only).
+    #[arg(short, long, default_value_t = 32)]
+    samples: usize,
+
+    /// Worker threads to render with. 0 lets rayon pick (one per core).
+    #[arg(short, long, default_value_t = 0)]
+    threads: usize,
 }
 
 struct Framebuffer {
@@ -35,13 +57,6 @@ impl Framebuffer {
             buf: vec![0; width * height * 3],
         }
     }
-
-    pub fn write(&mut self, i: usize, j: usize, color: Color) {
-        let p = (i * self.width + j) * 3;
-        self.buf[p + 0] = (255. * color.x) as u8;
-        self.buf[p + 1] = (255. * color.y) as u8;
-        self.buf[p + 2] = (255. * color.z) as u8;
-    }
 }
 
 struct Camera {
@@ -53,6 +68,8 @@ struct Camera {
     v: Vec3,
     w: Vec3,
     lens_radius: f32,
+    time0: f32,
+    time1: f32,
 }
 
 impl Camera {
@@ -87,63 +104,42 @@ impl Camera {
             v,
             w,
             lens_radius: aperture / 2.,
+            time0: 0.,
+            time1: 0.,
         }
     }
 
-    pub fn ray(&self, u: f32, v: f32) -> Ray {
-        let lenspos = self.lens_radius * rand::thread_rng().sample(UniformInDisc);
+    // opens the shutter over [time0, time1]; `ray` samples a time in that
+    // window for each ray, producing motion blur against moving primitives
+    pub fn with_shutter(mut self, time0: f32, time1: f32) -> Self {
+        self.time0 = time0;
+        self.time1 = time1;
+        self
+    }
+
+    pub fn ray(&self, u: f32, v: f32, rng: &mut dyn RngCore) -> Ray {
+        let lenspos = self.lens_radius * rng.sample(UniformInDisc);
         let offset = self.u * lenspos.x + self.v * lenspos.y;
+        let time = rng.gen_range(self.time0..=self.time1);
 
         Ray::new(
             self.origin + offset,
             self.lower_left + u * self.horiz + v * self.vert - self.origin - offset,
+            time,
         )
     }
 }
 
-fn color_ray(incoming_ray: &Ray, scene: &Scene, depth: usize, log: bool) -> Color {
-    if depth <= 0 {
-        return Color::zero();
-    }
-
-    if let Some((hit, material)) = scene.probe(incoming_ray, 0.001, f32::INFINITY) {
-        if log {
-            println!(
-                "hit {:?} on mat {:?} ({}) from incoming {:?}",
-                hit,
-                material,
-                if hit.front(incoming_ray) {
-                    "outside"
-                } else {
-                    "inside"
-                },
-                incoming_ray
-            );
-        }
-        if let ScatteringResult::Scattered { ray, attenuation } =
-            material.scatter(incoming_ray, &hit)
-        {
-            if log {
-                println!("  -> scattered to {:?} with atten {:?}", ray, attenuation);
-            }
-            return attenuation * color_ray(&ray, scene, depth - 1, log);
-        }
-
-        return Color::zero();
-    }
-
-    let t = 0.5 * (incoming_ray.dir.normalized().y + 1.);
-    (1. - t) * Color::one() + t * Color::new(0.5, 0.7, 1.0)
-}
-
 fn construct_test_scene() -> Scene {
     let mut scene = Scene::new();
+    scene.set_background((0.5, 0.7, 1.0).into());
 
     let material1 = Arc::new(Lambertian::new((0.3, 0.2, 0.8).into()));
     let material2 = Arc::new(Lambertian::new((0.8, 0.8, 0.0).into()));
     // let material3 = Arc::new(Metallic::new((0.8, 0.8, 0.8).into(), 0.3));
     let material3 = Arc::new(Dielectric::new(1.5));
     let material4 = Arc::new(Metallic::new((0.8, 0.6, 0.2).into(), 0.));
+    let light_material = Arc::new(DiffuseLight::new((4., 4., 4.).into()));
     scene.add(
         Box::new(Sphere {
             center: (0., 0., -1.).into(),
@@ -166,19 +162,36 @@ fn construct_test_scene() -> Scene {
         material3.clone(),
     );
     scene.add(
-        Box::new(Sphere {
-            center: (1., 0., -1.).into(),
+        Box::new(MovingSphere {
+            center0: (1., 0., -1.).into(),
+            center1: (1., 0.3, -1.).into(),
+            time0: 0.,
+            time1: 1.,
             radius: 0.5,
         }),
         material4.clone(),
     );
+    scene.add(
+        Box::new(Sphere {
+            center: (0., 2.5, -1.).into(),
+            radius: 0.5,
+        }),
+        light_material,
+    );
 
+    scene.build_bvh();
     scene
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
+    if args.threads > 0 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(args.threads)
+            .build_global()?;
+    }
+
     // -- framebuffer
     let aspect = 16. / 9.;
     let fb_width = 400;
@@ -189,40 +202,22 @@ fn main() -> Result<()> {
     let to = Vec3::new(0., 0., -1.);
     let focal_plane = (to - from).mag();
     let aperture = 2.;
-    let camera = Camera::new(
-        aspect,
-        20.,
-        aperture,
-        focal_plane,
-        from,
-        to,
-        (0., 1., 0.).into(),
-    );
+    let camera = Camera::new(aspect, 20., aperture, focal_plane, from, to, (0., 1., 0.).into())
+        .with_shutter(0., 1.);
 
     // -- camera
     let scene = construct_test_scene();
 
-    let mut rng = rand::thread_rng();
-    let supersamples = 32;
-
     // -- render
-    let pb = ProgressBar::new(fb.height as u64);
-    for i in 0..fb.height {
-        // render scanline
-        for j in 0..fb.width {
-            let mut color = Color::zero();
-            for _ in 0..supersamples {
-                let u = (j as f32 + rng.gen::<f32>()) / (fb_width - 1) as f32;
-                let v = 1. - (i as f32 + rng.gen::<f32>()) / (fb_height - 1) as f32;
-
-                let ray = camera.ray(u, v);
-                color += color_ray(&ray, &scene, 40, false);
-            }
-            color /= supersamples as f32;
-            color.apply(|x| x.powf(1. / 2.2));
-            fb.write(i, j, color);
-        }
-    }
+    let renderer: Box<dyn Renderer> = match args.renderer {
+        RendererKind::Path => Box::new(PathTracer {
+            supersamples: args.samples,
+            max_depth: 40,
+        }),
+        RendererKind::Normals => Box::new(NormalsRenderer),
+        RendererKind::Depth => Box::new(DepthRenderer { max_t: 10. }),
+    };
+    renderer.render(&scene, &camera, &mut fb);
 
     image::save_buffer(
         args.out,
@@ -1,4 +1,4 @@
-use rand::Rng;
+use rand::{Rng, RngCore};
 use std::fmt::Debug;
 use ultraviolet::Vec3;
 
@@ -10,8 +10,17 @@ pub enum ScatteringResult {
     Scattered { ray: Ray, attenuation: Color },
     Absorbed,
 }
-pub trait Material: Debug {
-    fn scatter(&self, incoming_ray: &Ray, hit: &Hit) -> ScatteringResult;
+pub trait Material: Debug + Send + Sync {
+    fn scatter(&self, incoming_ray: &Ray, hit: &Hit, rng: &mut dyn RngCore) -> ScatteringResult;
+
+    fn emitted(&self) -> Color {
+        Color::zero()
+    }
+
+    // None opts a material out of next-event estimation against scene lights
+    fn albedo(&self) -> Option<Color> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -26,18 +35,22 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, incoming_ray: &Ray, hit: &Hit) -> ScatteringResult {
-        let S = rand::thread_rng().sample(UniformOnSphere);
+    fn scatter(&self, incoming_ray: &Ray, hit: &Hit, rng: &mut dyn RngCore) -> ScatteringResult {
+        let S = rng.sample(UniformOnSphere);
         let mut scatter_dir = hit.normal + S;
         if scatter_dir.mag() < 0.00000001 {
             scatter_dir = hit.normal;
         }
 
         ScatteringResult::Scattered {
-            ray: Ray::new(hit.p, scatter_dir),
+            ray: Ray::new(hit.p, scatter_dir, incoming_ray.time),
             attenuation: self.albedo,
         }
     }
+
+    fn albedo(&self) -> Option<Color> {
+        Some(self.albedo)
+    }
 }
 
 #[derive(Debug)]
@@ -53,15 +66,15 @@ impl Metallic {
 }
 
 impl Material for Metallic {
-    fn scatter(&self, incoming_ray: &Ray, hit: &Hit) -> ScatteringResult {
+    fn scatter(&self, incoming_ray: &Ray, hit: &Hit, rng: &mut dyn RngCore) -> ScatteringResult {
         let mut refl = incoming_ray.dir.normalized().reflected(hit.normal);
-        refl += self.roughness * rand::thread_rng().sample(UniformInSphere);
+        refl += self.roughness * rng.sample(UniformInSphere);
 
         if refl.dot(hit.normal) < 0. {
             ScatteringResult::Absorbed
         } else {
             ScatteringResult::Scattered {
-                ray: Ray::new(hit.p, refl),
+                ray: Ray::new(hit.p, refl, incoming_ray.time),
                 attenuation: self.albedo,
             }
         }
@@ -95,15 +108,15 @@ fn reflectance(v: Vec3, normal: Vec3, eta: f32) -> f32 {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, incoming_ray: &Ray, hit: &Hit) -> ScatteringResult {
+    fn scatter(&self, incoming_ray: &Ray, hit: &Hit, rng: &mut dyn RngCore) -> ScatteringResult {
         let attenuation = Color::one();
-        let ratio = if hit.front(incoming_ray) {
+        let ratio = if hit.front_facing(incoming_ray) {
             1. / self.ior
         } else {
             self.ior
         };
         // make the normal always face outward
-        let n = if hit.front(incoming_ray) {
+        let n = if hit.front_facing(incoming_ray) {
             hit.normal
         } else {
             -hit.normal
@@ -112,15 +125,35 @@ impl Material for Dielectric {
         // let refracted = refract(incoming_ray.dir.normalized(), n, ratio);
         let mut scatter = incoming_ray.dir.normalized().refracted(n, ratio);
         if scatter.abs().component_max() <= f32::EPSILON
-            || reflectance(incoming_ray.dir.normalized(), n, ratio)
-                >= rand::thread_rng().gen::<f32>()
+            || reflectance(incoming_ray.dir.normalized(), n, ratio) >= rng.gen::<f32>()
         {
             scatter = incoming_ray.dir.normalized().reflected(hit.normal);
         }
 
         ScatteringResult::Scattered {
-            ray: Ray::new(hit.p, scatter),
+            ray: Ray::new(hit.p, scatter, incoming_ray.time),
             attenuation,
         }
     }
 }
+
+#[derive(Debug)]
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _incoming_ray: &Ray, _hit: &Hit, _rng: &mut dyn RngCore) -> ScatteringResult {
+        ScatteringResult::Absorbed
+    }
+
+    fn emitted(&self) -> Color {
+        self.emit
+    }
+}
@@ -1,7 +1,20 @@
 use rand::distributions::Distribution;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use rand_distr::{Normal, Uniform};
 use ultraviolet::Vec3;
 
+// deterministic per-pixel RNG; runs the seed through a splitmix64 finalizer
+// so adjacent pixels don't end up with correlated streams
+pub fn pixel_rng(i: usize, j: usize) -> StdRng {
+    let mut z = ((i as u64) << 32 | j as u64).wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+
+    StdRng::seed_from_u64(z)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct UniformInSphere;
 
@@ -19,6 +32,22 @@ impl Distribution<Vec3> for UniformInSphere {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct UniformInDisc;
+
+impl Distribution<Vec3> for UniformInDisc {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Vec3 {
+        let uniform = Uniform::<f32>::new(-1., 1.);
+
+        loop {
+            let p = Vec3::new(uniform.sample(rng), uniform.sample(rng), 0.);
+            if p.mag_sq() < 1. {
+                return p;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct UniformOnSphere;
 
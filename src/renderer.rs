@@ -0,0 +1,145 @@
+use indicatif::ProgressBar;
+use rand::{Rng, RngCore};
+use rayon::prelude::*;
+
+use crate::material::ScatteringResult;
+use crate::random::pixel_rng;
+use crate::trace::{Ray, Scene};
+use crate::{Camera, Color, Framebuffer};
+
+pub trait Renderer {
+    fn render(&self, scene: &Scene, camera: &Camera, fb: &mut Framebuffer);
+}
+
+// runs `shade` for every pixel of `fb`, one scanline per rayon work item;
+// `shade` gets its own RNG seeded from the pixel coordinates, so the image
+// doesn't depend on thread count or scheduling order
+fn render_tiled(
+    fb: &mut Framebuffer,
+    pb: &ProgressBar,
+    shade: impl Fn(usize, usize, &mut dyn RngCore) -> Color + Sync,
+) {
+    let width = fb.width;
+
+    fb.buf
+        .par_chunks_mut(width * 3)
+        .enumerate()
+        .for_each(|(i, row)| {
+            for (j, px) in row.chunks_mut(3).enumerate() {
+                let mut rng = pixel_rng(i, j);
+                let color = shade(i, j, &mut rng);
+                px[0] = (255. * color.x) as u8;
+                px[1] = (255. * color.y) as u8;
+                px[2] = (255. * color.z) as u8;
+            }
+            pb.inc(1);
+        });
+
+    pb.finish();
+}
+
+// `count_emitted` is false right after a bounce that already did NEE toward
+// scene lights, so that this hit's `emitted()` (if it landed on a light)
+// isn't added a second time on top of that NEE sample.
+fn color_ray(
+    incoming_ray: &Ray,
+    scene: &Scene,
+    depth: usize,
+    count_emitted: bool,
+    rng: &mut dyn RngCore,
+) -> Color {
+    if depth == 0 {
+        return Color::zero();
+    }
+
+    if let Some((hit, material)) = scene.probe(incoming_ray, 0.001, f32::INFINITY) {
+        let emitted = if count_emitted {
+            material.emitted()
+        } else {
+            Color::zero()
+        };
+        let direct = match material.albedo() {
+            Some(albedo) => scene.sample_direct(&hit, albedo, incoming_ray.time, rng),
+            None => Color::zero(),
+        };
+
+        if let ScatteringResult::Scattered { ray, attenuation } =
+            material.scatter(incoming_ray, &hit, rng)
+        {
+            let next = color_ray(&ray, scene, depth - 1, material.albedo().is_none(), rng);
+            return emitted + direct + attenuation * next;
+        }
+
+        return emitted + direct;
+    }
+
+    scene.background()
+}
+
+pub struct PathTracer {
+    pub supersamples: usize,
+    pub max_depth: usize,
+}
+
+impl Renderer for PathTracer {
+    fn render(&self, scene: &Scene, camera: &Camera, fb: &mut Framebuffer) {
+        let (width, height) = (fb.width, fb.height);
+        let pb = ProgressBar::new(height as u64);
+
+        render_tiled(fb, &pb, |i, j, rng| {
+            let mut color = Color::zero();
+            for _ in 0..self.supersamples {
+                let u = (j as f32 + rng.gen::<f32>()) / (width - 1) as f32;
+                let v = 1. - (i as f32 + rng.gen::<f32>()) / (height - 1) as f32;
+
+                let ray = camera.ray(u, v, rng);
+                color += color_ray(&ray, scene, self.max_depth, true, rng);
+            }
+            color /= self.supersamples as f32;
+            color.apply(|x| x.powf(1. / 2.2));
+            color
+        });
+    }
+}
+
+pub struct NormalsRenderer;
+
+impl Renderer for NormalsRenderer {
+    fn render(&self, scene: &Scene, camera: &Camera, fb: &mut Framebuffer) {
+        let (width, height) = (fb.width, fb.height);
+        let pb = ProgressBar::new(height as u64);
+
+        render_tiled(fb, &pb, |i, j, rng| {
+            let u = j as f32 / (width - 1) as f32;
+            let v = 1. - i as f32 / (height - 1) as f32;
+            let ray = camera.ray(u, v, rng);
+
+            match scene.probe(&ray, 0.001, f32::INFINITY) {
+                Some((hit, _)) => (hit.normal + Color::one()) * 0.5,
+                None => Color::zero(),
+            }
+        });
+    }
+}
+
+pub struct DepthRenderer {
+    pub max_t: f32,
+}
+
+impl Renderer for DepthRenderer {
+    fn render(&self, scene: &Scene, camera: &Camera, fb: &mut Framebuffer) {
+        let (width, height) = (fb.width, fb.height);
+        let pb = ProgressBar::new(height as u64);
+
+        render_tiled(fb, &pb, |i, j, rng| {
+            let u = j as f32 / (width - 1) as f32;
+            let v = 1. - i as f32 / (height - 1) as f32;
+            let ray = camera.ray(u, v, rng);
+
+            match scene.probe(&ray, 0.001, f32::INFINITY) {
+                Some((hit, _)) => Color::one() * (1. - (hit.t / self.max_t).min(1.)),
+                None => Color::zero(),
+            }
+        });
+    }
+}
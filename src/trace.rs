@@ -1,5 +1,9 @@
 use std::sync::Arc;
 
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+use crate::random::UniformOnSphere;
 use crate::{material::Material, Color};
 use ultraviolet::Vec3;
 
@@ -7,11 +11,12 @@ use ultraviolet::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub dir: Vec3,
+    pub time: f32,
 }
 
 impl Ray {
-    pub fn new(origin: Vec3, dir: Vec3) -> Self {
-        Self { origin, dir }
+    pub fn new(origin: Vec3, dir: Vec3, time: f32) -> Self {
+        Self { origin, dir, time }
     }
 
     pub fn at(&self, t: f32) -> Vec3 {
@@ -24,6 +29,9 @@ pub struct Hit {
     pub p: Vec3,
     pub t: f32,
     pub normal: Vec3,
+    // index into Scene::materials; set by the BVH leaves, so only meaningful
+    // for hits from Scene::probe after build_bvh (otherwise it is 0)
+    pub obj_index: usize,
 }
 
 impl Hit {
@@ -32,8 +40,79 @@ impl Hit {
     }
 }
 
-pub trait Probe {
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    // slab test: shrink t_min..t_max to the span during which the ray is
+    // inside every axis' slab, rejecting as soon as the span goes empty
+    pub fn hit(&self, ray: &Ray, mut t_min: f32, mut t_max: f32) -> bool {
+        for axis in 0..3 {
+            let inv_d = 1. / component(ray.dir, axis);
+            let mut t0 = (component(self.min, axis) - component(ray.origin, axis)) * inv_d;
+            let mut t1 = (component(self.max, axis) - component(ray.origin, axis)) * inv_d;
+            if inv_d < 0. {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn surrounding(&self, other: &Aabb) -> Aabb {
+        let min = Vec3::new(
+            self.min.x.min(other.min.x),
+            self.min.y.min(other.min.y),
+            self.min.z.min(other.min.z),
+        );
+        let max = Vec3::new(
+            self.max.x.max(other.max.x),
+            self.max.y.max(other.max.y),
+            self.max.z.max(other.max.z),
+        );
+
+        Aabb::new(min, max)
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+}
+
+fn component(v: Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        2 => v.z,
+        _ => unreachable!("axis out of range"),
+    }
+}
+
+pub trait Probe: Send + Sync {
     fn probe(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit>;
+    fn bounding_box(&self) -> Aabb;
+
+    // surface area and a uniform point+normal sample, used by Scene's light
+    // sampling; primitives that are never used as lights can leave these
+    fn area(&self) -> f32 {
+        0.
+    }
+
+    fn sample(&self, _rng: &mut dyn RngCore) -> (Vec3, Vec3) {
+        (Vec3::zero(), Vec3::unit_y())
+    }
 }
 
 pub struct Sphere {
@@ -67,13 +146,171 @@ impl Probe for Sphere {
             t: root,
             p,
             normal: (p - self.center) / self.radius,
+            obj_index: 0,
         })
     }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(
+            self.center - Vec3::broadcast(self.radius),
+            self.center + Vec3::broadcast(self.radius),
+        )
+    }
+
+    fn area(&self) -> f32 {
+        4. * core::f32::consts::PI * self.radius * self.radius
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> (Vec3, Vec3) {
+        let normal = rng.sample(UniformOnSphere);
+        (self.center + self.radius * normal, normal)
+    }
+}
+
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+}
+
+impl MovingSphere {
+    fn center(&self, time: f32) -> Vec3 {
+        self.center0
+            + ((time - self.time0) / (self.time1 - self.time0)) * (self.center1 - self.center0)
+    }
+}
+
+impl Probe for MovingSphere {
+    fn probe(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let center = self.center(ray.time);
+
+        let sep = ray.origin - center;
+        let a = ray.dir.mag_sq();
+        let hb = sep.dot(ray.dir);
+        let c = sep.dot(sep) - self.radius * self.radius;
+        let discrim = hb * hb - a * c;
+
+        if discrim < 0. {
+            return None;
+        }
+        let sqd = discrim.sqrt();
+
+        let mut root = (-hb - sqd) / a;
+        if root < t_min || t_max < root {
+            root = (-hb + sqd) / a;
+            if root < t_min || t_max < root {
+                return None;
+            }
+        }
+
+        let p = ray.at(root);
+        Some(Hit {
+            t: root,
+            p,
+            normal: (p - center) / self.radius,
+            obj_index: 0,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let at_time0 = Aabb::new(
+            self.center(self.time0) - Vec3::broadcast(self.radius),
+            self.center(self.time0) + Vec3::broadcast(self.radius),
+        );
+        let at_time1 = Aabb::new(
+            self.center(self.time1) - Vec3::broadcast(self.radius),
+            self.center(self.time1) + Vec3::broadcast(self.radius),
+        );
+
+        at_time0.surrounding(&at_time1)
+    }
+}
+
+// wraps a scene primitive so a hit coming back through the BVH can still be
+// traced to its material in Scene::materials
+struct BvhLeaf {
+    index: usize,
+    object: Arc<dyn Probe>,
+}
+
+impl Probe for BvhLeaf {
+    fn probe(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        self.object.probe(ray, t_min, t_max).map(|hit| Hit {
+            obj_index: self.index,
+            ..hit
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+}
+
+pub struct BvhNode {
+    left: Box<dyn Probe>,
+    right: Box<dyn Probe>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    // splits objects along a randomly chosen axis by centroid and recurses;
+    // may return a bare leaf if objects held only one primitive
+    fn build(mut objects: Vec<Box<dyn Probe>>, rng: &mut dyn RngCore) -> Box<dyn Probe> {
+        assert!(!objects.is_empty(), "BvhNode::build called with no objects");
+
+        match objects.len() {
+            1 => objects.pop().unwrap(),
+            2 => {
+                let right = objects.pop().unwrap();
+                let left = objects.pop().unwrap();
+                let bbox = left.bounding_box().surrounding(&right.bounding_box());
+                Box::new(BvhNode { left, right, bbox })
+            }
+            _ => {
+                let axis = rng.gen_range(0..3usize);
+                objects.sort_by(|a, b| {
+                    let ca = component(a.bounding_box().centroid(), axis);
+                    let cb = component(b.bounding_box().centroid(), axis);
+                    ca.partial_cmp(&cb).unwrap()
+                });
+
+                let rest = objects.split_off(objects.len() / 2);
+                let left = Self::build(objects, rng);
+                let right = Self::build(rest, rng);
+
+                let bbox = left.bounding_box().surrounding(&right.bounding_box());
+                Box::new(BvhNode { left, right, bbox })
+            }
+        }
+    }
+}
+
+impl Probe for BvhNode {
+    fn probe(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        let hit_left = self.left.probe(ray, t_min, t_max);
+        let closest = hit_left.as_ref().map_or(t_max, |hit| hit.t);
+        let hit_right = self.right.probe(ray, t_min, closest);
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
 }
 
 pub struct Scene {
-    objects: Vec<Box<dyn Probe>>,
+    objects: Vec<Arc<dyn Probe>>,
     materials: Vec<Arc<dyn Material>>,
+    bvh: Option<Box<dyn Probe>>,
+    emitters: Vec<usize>,
+    background: Option<Color>,
 }
 
 impl Scene {
@@ -81,15 +318,99 @@ impl Scene {
         Self {
             objects: vec![],
             materials: vec![],
+            bvh: None,
+            emitters: vec![],
+            background: None,
         }
     }
 
+    pub fn set_background(&mut self, color: Color) {
+        self.background = Some(color);
+    }
+
+    pub fn background(&self) -> Color {
+        self.background.unwrap_or(Color::zero())
+    }
+
     pub fn add(&mut self, object: Box<dyn Probe>, material: Arc<dyn Material>) {
-        self.objects.push(object);
+        if material.emitted().mag_sq() > 0. {
+            self.emitters.push(self.objects.len());
+        }
+
+        self.objects.push(Arc::from(object));
         self.materials.push(material);
     }
 
+    // next-event estimation: sample a random emitter and return its
+    // contribution to `hit`, or black if occluded or the scene has no lights
+    pub fn sample_direct(
+        &self,
+        hit: &Hit,
+        albedo: Color,
+        time: f32,
+        rng: &mut dyn RngCore,
+    ) -> Color {
+        if self.emitters.is_empty() {
+            return Color::zero();
+        }
+
+        let light_index = self.emitters[rng.gen_range(0..self.emitters.len())];
+        let light = &self.objects[light_index];
+        let emit = self.materials[light_index].emitted();
+
+        let (point, light_normal) = light.sample(rng);
+        let to_light = point - hit.p;
+        let distance = to_light.mag();
+        let dir = to_light / distance;
+
+        let cos_theta = hit.normal.dot(dir).max(0.);
+        let cos_light = (-dir).dot(light_normal).max(0.);
+        if cos_theta <= 0. || cos_light <= 0. {
+            return Color::zero();
+        }
+
+        let shadow_ray = Ray::new(hit.p, dir, time);
+        if self.probe(&shadow_ray, 0.001, distance - 0.001).is_some() {
+            return Color::zero();
+        }
+
+        let pdf_light = distance * distance / (cos_light * light.area());
+        // `emitters.len()` lights exist but only one was sampled uniformly
+        // at random, so divide by its selection probability (1 / len) to stay
+        // unbiased.
+        self.emitters.len() as f32 * emit * albedo * (cos_theta / core::f32::consts::PI)
+            / pdf_light
+    }
+
+    // call once after the last `add`; `probe` uses the tree when present.
+    // a no-op with no objects: BvhNode::build can't handle an empty list,
+    // and an empty scene is already handled correctly by the linear scan.
+    pub fn build_bvh(&mut self) {
+        if self.objects.is_empty() {
+            return;
+        }
+
+        let leaves = self
+            .objects
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(index, object)| -> Box<dyn Probe> { Box::new(BvhLeaf { index, object }) })
+            .collect();
+
+        // fixed seed: BVH shape should be reproducible like the rest of the
+        // render, not depend on whatever thread_rng happened to draw
+        let mut rng = StdRng::seed_from_u64(0);
+        self.bvh = Some(BvhNode::build(leaves, &mut rng));
+    }
+
     pub fn probe(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(Hit, &dyn Material)> {
+        if let Some(bvh) = &self.bvh {
+            return bvh
+                .probe(ray, t_min, t_max)
+                .map(|hit| (hit, self.materials[hit.obj_index].as_ref()));
+        }
+
         let mut closest = t_max;
         let mut current_hit = None;
 